@@ -0,0 +1,226 @@
+//! Chromosome-name interning, per the crate's own TODO to replace the
+//! per-site `chrom: Vec<u8>` with an index into a shared dictionary:
+//! genome-wide count files repeat the same few dozen names millions
+//! of times, so holding one copy per name instead of one per site is
+//! a large memory win.
+
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::str;
+
+use rustc_hash::FxHashMap;
+
+use crate::error::MSiteError;
+use crate::reader::MSiteReader;
+use crate::MSite;
+
+/// Interns chromosome names into small `u32` ids.
+///
+/// Ids are assigned in first-seen order starting at `0` and are
+/// stable for the lifetime of the dictionary, so they can be used in
+/// place of the name itself in any structure that is built while this
+/// dictionary is also being populated.
+#[derive(Debug, Default)]
+pub struct ChromDict {
+    name_to_id: FxHashMap<Vec<u8>, u32>,
+    id_to_name: Vec<Vec<u8>>,
+}
+
+impl ChromDict {
+    pub fn new() -> ChromDict {
+        ChromDict::default()
+    }
+
+    /// Look up `name`'s id, interning it if this is the first time it
+    /// has been seen.
+    pub fn intern(&mut self, name: &[u8]) -> u32 {
+        if let Some(&id) = self.name_to_id.get(name) {
+            return id;
+        }
+        let id = self.id_to_name.len() as u32;
+        self.id_to_name.push(name.to_vec());
+        self.name_to_id.insert(name.to_vec(), id);
+        id
+    }
+
+    /// The name associated with `id`.
+    ///
+    /// Panics if `id` was not produced by this dictionary's
+    /// [`ChromDict::intern`].
+    pub fn name(&self, id: u32) -> &[u8] {
+        &self.id_to_name[id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.id_to_name.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id_to_name.is_empty()
+    }
+}
+
+/// An [`MSite`] whose `chrom` has been replaced with an id into a
+/// [`ChromDict`], for memory-dense storage of genome-wide data.
+///
+/// Deliberately has no `PartialOrd`/`Ord`: the `chrom` field is an id
+/// in first-seen order, not name order, so deriving a comparison from
+/// the struct's fields directly would sort genome-wide data
+/// differently from `MSite` (and silently so). Use
+/// [`MSiteIndexed::cmp_with_dict`] instead.
+#[derive(Debug, PartialEq)]
+pub struct MSiteIndexed {
+    pub chrom: u32,
+    pub pos: u64,
+    pub strand: char,
+    pub context: Vec<u8>,
+    pub meth: f64,
+    pub n_reads: u64,
+}
+
+impl MSiteIndexed {
+    /// Build an `MSiteIndexed` from an `MSite`, interning its chrom
+    /// name into `dict`.
+    pub fn from_msite(site: MSite, dict: &mut ChromDict) -> MSiteIndexed {
+        MSiteIndexed {
+            chrom: dict.intern(&site.chrom),
+            pos: site.pos,
+            strand: site.strand,
+            context: site.context,
+            meth: site.meth,
+            n_reads: site.n_reads,
+        }
+    }
+
+    /// The chrom name, looked up in `dict`.
+    pub fn chrom_name<'d>(&self, dict: &'d ChromDict) -> &'d [u8] {
+        dict.name(self.chrom)
+    }
+
+    /// Order sites by chromosome name, then position, the leading keys
+    /// of [`MSite`]'s `Ord`. Sites with the same name and position
+    /// compare `Equal` here, whereas `MSite`'s `Ord` goes on to compare
+    /// strand, context, `meth` and `n_reads`. Unlike `MSite`, this
+    /// cannot be a plain `Ord` impl since the name lives in the shared
+    /// `dict` rather than on the site itself.
+    pub fn cmp_with_dict(&self, other: &MSiteIndexed, dict: &ChromDict) -> Ordering {
+        dict.name(self.chrom)
+            .cmp(dict.name(other.chrom))
+            .then_with(|| self.pos.cmp(&other.pos))
+    }
+
+    /// Write this site in the same tab-separated format as
+    /// `MSite`'s `Display` impl, resolving the chrom name via `dict`.
+    pub fn write_to<W: Write>(&self, w: &mut W, dict: &ChromDict) -> io::Result<()> {
+        const DIGITER: f64 = 1_000_000.0;
+        let m = (self.meth * DIGITER).round() / DIGITER;
+        writeln!(
+            w,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            str::from_utf8(self.chrom_name(dict)).unwrap(),
+            self.pos,
+            self.strand,
+            str::from_utf8(&self.context).unwrap(),
+            m,
+            self.n_reads
+        )
+    }
+}
+
+/// Adapts an [`MSiteReader`] to yield [`MSiteIndexed`], interning each
+/// parsed site's chrom name into `dict` as it is read.
+pub struct MSiteIndexedReader<'d> {
+    reader: MSiteReader,
+    dict: &'d mut ChromDict,
+}
+
+impl<'d> MSiteIndexedReader<'d> {
+    pub fn new(reader: MSiteReader, dict: &'d mut ChromDict) -> MSiteIndexedReader<'d> {
+        MSiteIndexedReader { reader, dict }
+    }
+}
+
+impl<'d> Iterator for MSiteIndexedReader<'d> {
+    type Item = Result<MSiteIndexed, MSiteError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(
+            self.reader
+                .next()?
+                .map(|site| MSiteIndexed::from_msite(site, self.dict)),
+        )
+    }
+}
+
+impl MSiteReader {
+    /// Adapt this reader to yield [`MSiteIndexed`], populating `dict`
+    /// with each newly-seen chrom name as parsing proceeds.
+    pub fn indexed(self, dict: &mut ChromDict) -> MSiteIndexedReader<'_> {
+        MSiteIndexedReader::new(self, dict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_assigns_ids_in_first_seen_order_and_reuses_them() {
+        let mut dict = ChromDict::new();
+        assert_eq!(dict.intern(b"chr2"), 0);
+        assert_eq!(dict.intern(b"chr1"), 1);
+        assert_eq!(dict.intern(b"chr2"), 0);
+        assert_eq!(dict.len(), 2);
+    }
+
+    #[test]
+    fn chrom_name_round_trips_through_from_msite() {
+        let mut dict = ChromDict::new();
+        let site = MSite::build("chr3 10 + CpG 0.5 10", 1).unwrap();
+        let indexed = MSiteIndexed::from_msite(site, &mut dict);
+        assert_eq!(indexed.chrom_name(&dict), b"chr3");
+    }
+
+    #[test]
+    fn write_to_matches_msite_display_format() {
+        let mut dict = ChromDict::new();
+        let line = "chr1 1000 - CHH 0.8 10".to_string();
+        let site = MSite::build(&line, 1).unwrap();
+        let expected = format!("{site}\n");
+
+        let indexed = MSiteIndexed::from_msite(site, &mut dict);
+        let mut buf = Vec::new();
+        indexed.write_to(&mut buf, &dict).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[test]
+    fn cmp_with_dict_orders_by_name_then_position_despite_id_order() {
+        let mut dict = ChromDict::new();
+        // intern "chr2" first so its id (0) sorts before "chr10"'s
+        // id (1) -- the opposite of name order -- to prove
+        // cmp_with_dict does not just compare ids
+        let chr2_first = MSiteIndexed::from_msite(
+            MSite::build("chr2 5 + CpG 0.0 10", 1).unwrap(),
+            &mut dict,
+        );
+        let chr10_second = MSiteIndexed::from_msite(
+            MSite::build("chr10 1 + CpG 0.0 10", 2).unwrap(),
+            &mut dict,
+        );
+        assert!(chr2_first.chrom < chr10_second.chrom);
+        assert_eq!(
+            chr2_first.cmp_with_dict(&chr10_second, &dict),
+            Ordering::Greater
+        );
+
+        let same_chrom_later_pos = MSiteIndexed::from_msite(
+            MSite::build("chr2 50 + CpG 0.0 10", 3).unwrap(),
+            &mut dict,
+        );
+        assert_eq!(
+            chr2_first.cmp_with_dict(&same_chrom_later_pos, &dict),
+            Ordering::Less
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Error types produced while parsing [`MSite`](crate::MSite) records
+//! from text input, with enough context (source line number and
+//! offending field name) for callers to report a useful diagnostic.
+
+use std::fmt;
+use std::io;
+
+/// A failure to parse a single line of an MSite-formatted file.
+///
+/// Every variant carries the 1-based `line` number of the offending
+/// record so a [`MSiteReader`](crate::reader::MSiteReader) can surface
+/// precise diagnostics without the caller having to track position
+/// itself.
+#[derive(Debug)]
+pub enum MSiteError {
+    /// A required field was absent from the line.
+    MissingField { line: usize, field: &'static str },
+    /// A field expected to parse as an integer did not.
+    BadInt {
+        line: usize,
+        field: &'static str,
+        value: String,
+    },
+    /// A field expected to parse as a single character did not.
+    BadChar {
+        line: usize,
+        field: &'static str,
+        value: String,
+    },
+    /// A field expected to parse as a floating point number did not.
+    BadFloat {
+        line: usize,
+        field: &'static str,
+        value: String,
+    },
+    /// The methylation level was parsed but falls outside `[0, 1]`.
+    MethOutOfRange { line: usize, value: f64 },
+    /// The underlying reader failed.
+    Io { line: usize, source: io::Error },
+}
+
+impl fmt::Display for MSiteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MSiteError::MissingField { line, field } => {
+                write!(f, "line {line}: missing field `{field}`")
+            }
+            MSiteError::BadInt { line, field, value } => {
+                write!(f, "line {line}: field `{field}` is not a valid integer: `{value}`")
+            }
+            MSiteError::BadChar { line, field, value } => {
+                write!(f, "line {line}: field `{field}` is not a valid character: `{value}`")
+            }
+            MSiteError::BadFloat { line, field, value } => {
+                write!(f, "line {line}: field `{field}` is not a valid float: `{value}`")
+            }
+            MSiteError::MethOutOfRange { line, value } => {
+                write!(f, "line {line}: methylation level {value} not in [0, 1]")
+            }
+            MSiteError::Io { line, source } => {
+                write!(f, "line {line}: {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MSiteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MSiteError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
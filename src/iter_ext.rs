@@ -0,0 +1,248 @@
+//! Iterator adapters over streams of [`MSite`], exposed through a
+//! blanket extension trait so they compose with
+//! [`MSiteReader`](crate::reader::MSiteReader) and with each other
+//! without collecting a whole file into memory.
+
+use std::iter::Peekable;
+
+use crate::MSite;
+
+/// Collapses a position-sorted stream of `MSite` into symmetric CpGs.
+///
+/// Returned by [`MSiteIteratorExt::symmetric_cpgs`].
+pub struct SymmetricCpgs<I: Iterator<Item = MSite>> {
+    iter: Peekable<I>,
+    keep_non_cpg: bool,
+}
+
+impl<I: Iterator<Item = MSite>> Iterator for SymmetricCpgs<I> {
+    type Item = MSite;
+
+    fn next(&mut self) -> Option<MSite> {
+        loop {
+            let mut cur = self.iter.next()?;
+            if !cur.is_cpg() {
+                if self.keep_non_cpg {
+                    return Some(cur);
+                }
+                continue;
+            }
+            if matches!(self.iter.peek(), Some(next) if cur.is_mate_of(next)) {
+                let next = self.iter.next().expect("peeked Some above");
+                cur.add(&next);
+                cur.strand = '+';
+            }
+            return Some(cur);
+        }
+    }
+}
+
+/// Drops sites below a minimum read depth.
+///
+/// Returned by [`MSiteIteratorExt::filter_coverage`].
+pub struct FilterCoverage<I> {
+    iter: I,
+    min_reads: u64,
+}
+
+impl<I: Iterator<Item = MSite>> Iterator for FilterCoverage<I> {
+    type Item = MSite;
+
+    fn next(&mut self) -> Option<MSite> {
+        self.iter.by_ref().find(|site| site.n_reads >= self.min_reads)
+    }
+}
+
+/// Genome-wide totals produced by [`MSiteIteratorExt::summarize`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MSiteSummary {
+    pub n_sites: u64,
+    pub n_reads: u64,
+    pub n_meth: u64,
+    pub n_cpg: u64,
+    pub n_chh: u64,
+    pub n_ccg: u64,
+    pub n_cxg: u64,
+}
+
+impl MSiteSummary {
+    /// The read-weighted mean methylation level, `sum(n_meth) /
+    /// sum(n_reads)`, as opposed to an unweighted average of the
+    /// per-site `meth` fractions. Returns `0.0` when there are no
+    /// reads at all.
+    pub fn mean_methylation(&self) -> f64 {
+        if self.n_reads == 0 {
+            0.0
+        } else {
+            self.n_meth as f64 / self.n_reads as f64
+        }
+    }
+}
+
+/// Extension methods for streams of [`MSite`].
+pub trait MSiteIteratorExt: Iterator<Item = MSite> + Sized {
+    /// Merge each plus-strand CpG with its minus-strand mate one
+    /// position downstream, per [`MSite::is_mate_of`] /
+    /// [`MSite::add`].
+    ///
+    /// The input must be sorted by position. Mated pairs are emitted
+    /// as a single site on the `+` strand with combined counts;
+    /// unmated CpGs pass through unchanged. When `keep_non_cpg` is
+    /// `false`, sites outside a CpG context are dropped rather than
+    /// passed through. Runs in `O(1)` memory beyond the one site of
+    /// lookahead required to detect a mate.
+    fn symmetric_cpgs(self, keep_non_cpg: bool) -> SymmetricCpgs<Self> {
+        SymmetricCpgs {
+            iter: self.peekable(),
+            keep_non_cpg,
+        }
+    }
+
+    /// Drop sites with fewer than `min_reads` total reads.
+    fn filter_coverage(self, min_reads: u64) -> FilterCoverage<Self> {
+        FilterCoverage {
+            iter: self,
+            min_reads,
+        }
+    }
+
+    /// Consume the stream into genome-wide totals: overall read and
+    /// methylated-read counts, per-context site counts, and the
+    /// read-weighted mean methylation level.
+    fn summarize(self) -> MSiteSummary {
+        let mut summary = MSiteSummary::default();
+        for site in self {
+            summary.n_sites += 1;
+            summary.n_reads += site.n_reads;
+            summary.n_meth += site.n_meth();
+            if site.is_cpg() {
+                summary.n_cpg += 1;
+            } else if site.is_chh() {
+                summary.n_chh += 1;
+            } else if site.is_ccg() {
+                summary.n_ccg += 1;
+            } else if site.is_cxg() {
+                summary.n_cxg += 1;
+            }
+        }
+        summary
+    }
+}
+
+impl<I: Iterator<Item = MSite>> MSiteIteratorExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(line: &str) -> MSite {
+        MSite::build(line, 1).unwrap()
+    }
+
+    #[test]
+    fn symmetric_cpgs_merges_a_mated_pair_into_one_plus_strand_site() {
+        let sites = vec![
+            site("chr1 10 + CpG 1.0 10"),
+            site("chr1 11 - CpG 0.0 6"),
+        ];
+        let merged: Vec<MSite> = sites.into_iter().symmetric_cpgs(true).collect();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].pos, 10);
+        assert_eq!(merged[0].strand, '+');
+        assert_eq!(merged[0].n_reads, 16);
+        assert_eq!(merged[0].n_meth(), 10);
+    }
+
+    #[test]
+    fn symmetric_cpgs_passes_through_an_unmated_cpg() {
+        let sites = vec![site("chr1 10 + CpG 1.0 10")];
+        let result: Vec<MSite> = sites.into_iter().symmetric_cpgs(true).collect();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].pos, 10);
+        assert_eq!(result[0].strand, '+');
+        assert_eq!(result[0].n_reads, 10);
+    }
+
+    #[test]
+    fn symmetric_cpgs_drops_or_keeps_non_cpg_context() {
+        let sites = || {
+            vec![
+                site("chr1 10 + CHH 1.0 10"),
+                site("chr1 20 + CpG 1.0 10"),
+            ]
+        };
+        let dropped: Vec<MSite> = sites().into_iter().symmetric_cpgs(false).collect();
+        assert_eq!(dropped.len(), 1);
+        assert!(dropped[0].is_cpg());
+
+        let kept: Vec<MSite> = sites().into_iter().symmetric_cpgs(true).collect();
+        assert_eq!(kept.len(), 2);
+    }
+
+    #[test]
+    fn symmetric_cpgs_propagates_mutation_flag_from_either_mate() {
+        let sites = vec![
+            site("chr1 10 + CpGx 1.0 10"),
+            site("chr1 11 - CpG 0.0 6"),
+        ];
+        let merged: Vec<MSite> = sites.into_iter().symmetric_cpgs(true).collect();
+        assert_eq!(merged.len(), 1);
+        assert!(merged[0].is_mutated());
+
+        let sites = vec![
+            site("chr1 10 + CpG 1.0 10"),
+            site("chr1 11 - CpGx 0.0 6"),
+        ];
+        let merged: Vec<MSite> = sites.into_iter().symmetric_cpgs(true).collect();
+        assert_eq!(merged.len(), 1);
+        assert!(merged[0].is_mutated());
+    }
+
+    #[test]
+    fn filter_coverage_keeps_sites_at_the_threshold() {
+        let sites = vec![
+            site("chr1 1 + CpG 0.5 4"),
+            site("chr1 2 + CpG 0.5 5"),
+            site("chr1 3 + CpG 0.5 6"),
+        ];
+        let kept: Vec<u64> = sites
+            .into_iter()
+            .filter_coverage(5)
+            .map(|s| s.n_reads)
+            .collect();
+        assert_eq!(kept, vec![5, 6]);
+    }
+
+    #[test]
+    fn summarize_computes_read_weighted_mean_and_per_context_counts() {
+        // one heavily-covered unmethylated site and one lightly-covered
+        // fully-methylated site: the unweighted average of the two
+        // `meth` fractions is 0.5, but the read-weighted mean is
+        // pulled far below that by the high-coverage site
+        let sites = vec![
+            site("chr1 1 + CpG 0.0 90"),
+            site("chr1 2 + CHH 1.0 10"),
+        ];
+        let summary = sites.into_iter().summarize();
+
+        assert_eq!(summary.n_sites, 2);
+        assert_eq!(summary.n_reads, 100);
+        assert_eq!(summary.n_meth, 10);
+        assert_eq!(summary.n_cpg, 1);
+        assert_eq!(summary.n_chh, 1);
+        assert_eq!(summary.n_ccg, 0);
+        assert_eq!(summary.n_cxg, 0);
+
+        let weighted = summary.mean_methylation();
+        let unweighted = 0.5;
+        assert!((weighted - 0.1).abs() < 1e-9);
+        assert!((weighted - unweighted).abs() > 0.1);
+    }
+
+    #[test]
+    fn summarize_of_empty_stream_has_zero_mean_methylation() {
+        let summary = Vec::<MSite>::new().into_iter().summarize();
+        assert_eq!(summary.n_sites, 0);
+        assert_eq!(summary.mean_methylation(), 0.0);
+    }
+}
@@ -31,18 +31,28 @@
 //! Current tasks:
 //!
 //! - [ ] Replace the `chrom` instance variable with an index that
-//!       points to the chrom.
+//!   points to the chrom.
 //! - [ ] Allow for a dictionary to be used when the names of
-//!       chromosomes are needed.
+//!   chromosomes are needed.
 //! - [ ] Replace the type of `n_reads` with a template so they can be
-//!       smaller if desired.
+//!   smaller if desired.
 //! - [ ] Replace the `context` with an index to contexts.
 //! - [ ] Replace the `meth` variable with an integer value and have
-//!       the fractional value calculated when needed.
+//!   the fractional value calculated when needed.
+
+mod chrom_dict;
+mod error;
+mod iter_ext;
+pub mod reader;
+mod stats;
+
+pub use chrom_dict::{ChromDict, MSiteIndexed, MSiteIndexedReader};
+pub use error::MSiteError;
+pub use iter_ext::{FilterCoverage, MSiteIteratorExt, MSiteSummary, SymmetricCpgs};
+pub use reader::MSiteReader;
 
 use std::cmp::max;
 use std::cmp::Ordering;
-use std::error::Error;
 use std::str;
 
 #[derive(Debug, PartialEq, PartialOrd)]
@@ -57,9 +67,19 @@ pub struct MSite {
 
 impl Eq for MSite {}
 
+// `meth` is an f64, so `Ord` cannot be derived alongside `PartialOrd`;
+// the two are kept consistent by hand since every `MSite` compared
+// here comes from `build`, which already rejects non-finite `meth`.
+#[allow(clippy::derive_ord_xor_partial_ord)]
 impl Ord for MSite {
     fn cmp(&self, other: &MSite) -> Ordering {
-        self.partial_cmp(&other).unwrap()
+        self.partial_cmp(other).unwrap()
+    }
+}
+
+impl Default for MSite {
+    fn default() -> MSite {
+        MSite::new()
     }
 }
 
@@ -75,37 +95,58 @@ impl MSite {
         }
     }
 
-    pub fn build(s: &String) -> Result<MSite, Box<dyn Error>> {
+    /// Parse a single line of an MSite-formatted file.
+    ///
+    /// `line` is the 1-based position of `s` within its source and is
+    /// only used to annotate any [`MSiteError`] that results; callers
+    /// reading a single, standalone string may pass `0`.
+    pub fn build(s: &str, line: usize) -> Result<MSite, MSiteError> {
         let mut parts = s.split_whitespace();
 
         let chrom = match parts.next() {
             Some(part) => part.to_string().into_bytes(),
-            None => return Err("failed to extract chrom".into()),
+            None => return Err(MSiteError::MissingField { line, field: "chrom" }),
         };
         let pos = match parts.next() {
-            Some(part) => part.parse::<u64>().unwrap(),
-            None => return Err("failed to extract pos".into()),
+            Some(part) => part.parse::<u64>().map_err(|_| MSiteError::BadInt {
+                line,
+                field: "pos",
+                value: part.to_string(),
+            })?,
+            None => return Err(MSiteError::MissingField { line, field: "pos" }),
         };
         let strand = match parts.next() {
-            Some(part) => part.parse::<char>().unwrap(),
-            None => return Err("failed to extract strand".into()),
+            Some(part) => part.parse::<char>().map_err(|_| MSiteError::BadChar {
+                line,
+                field: "strand",
+                value: part.to_string(),
+            })?,
+            None => return Err(MSiteError::MissingField { line, field: "strand" }),
         };
         let context = match parts.next() {
             Some(part) => part.to_string().into_bytes(),
-            None => return Err("failed to extract context".into()),
+            None => return Err(MSiteError::MissingField { line, field: "context" }),
         };
         let meth = match parts.next() {
-            Some(part) => part.parse::<f64>().unwrap(),
-            None => return Err("failed to extract meth".into()),
+            Some(part) => part.parse::<f64>().map_err(|_| MSiteError::BadFloat {
+                line,
+                field: "meth",
+                value: part.to_string(),
+            })?,
+            None => return Err(MSiteError::MissingField { line, field: "meth" }),
         };
 
-        if meth < 0.0 || meth > 1.0 {
-            return Err("methylation level not in range".into());
+        if !(0.0..=1.0).contains(&meth) {
+            return Err(MSiteError::MethOutOfRange { line, value: meth });
         }
 
         let n_reads = match parts.next() {
-            Some(part) => part.parse::<u64>().unwrap(),
-            None => return Err("failed to extract n_reads".into()),
+            Some(part) => part.parse::<u64>().map_err(|_| MSiteError::BadInt {
+                line,
+                field: "n_reads",
+                value: part.to_string(),
+            })?,
+            None => return Err(MSiteError::MissingField { line, field: "n_reads" }),
         };
 
         Ok(MSite {
@@ -118,10 +159,10 @@ impl MSite {
         })
     }
     pub fn n_meth(&self) -> u64 {
-        return ((self.n_reads as f64) * self.meth).round() as u64;
+        ((self.n_reads as f64) * self.meth).round() as u64
     }
     pub fn n_umeth(&self) -> u64 {
-        return self.n_reads - self.n_meth();
+        self.n_reads - self.n_meth()
     }
     pub fn is_cpg(&self) -> bool {
         self.context.len() >= 3
@@ -171,6 +212,31 @@ impl MSite {
             self.context.pop();
         }
     }
+    /// Two-sided Fisher's exact test p-value for the null hypothesis
+    /// that `self` and `other` share the same methylation level,
+    /// based on the 2x2 table of methylated/unmethylated read counts
+    /// `[[n_meth_a, n_umeth_a], [n_meth_b, n_umeth_b]]`.
+    ///
+    /// Returns `1.0` for sites with zero total coverage. Panics if
+    /// `self` and `other` do not share `chrom`, `pos`, and `context`,
+    /// since a diff test only makes sense between the same site in
+    /// two samples.
+    pub fn diff_test(&self, other: &MSite) -> f64 {
+        assert_eq!(self.chrom, other.chrom, "diff_test: chrom mismatch");
+        assert_eq!(self.pos, other.pos, "diff_test: pos mismatch");
+        assert_eq!(self.context, other.context, "diff_test: context mismatch");
+
+        if self.n_reads == 0 && other.n_reads == 0 {
+            return 1.0;
+        }
+
+        stats::fisher_exact(
+            self.n_meth(),
+            self.n_umeth(),
+            other.n_meth(),
+            other.n_umeth(),
+        )
+    }
 }
 
 impl std::fmt::Display for MSite {
@@ -206,7 +272,7 @@ mod tests {
             meth: 0.0,
             n_reads: 0,
         };
-        let the_site = MSite::build(&valid_line1).unwrap_or_else(|err| {
+        let the_site = MSite::build(&valid_line1, 1).unwrap_or_else(|err| {
             eprintln!("failed parsing site: {err} {valid_line1}");
             std::process::exit(1);
         });
@@ -221,7 +287,7 @@ mod tests {
             meth: 0.8,
             n_reads: 10,
         };
-        let the_site = MSite::build(&valid_line2).unwrap_or_else(|err| {
+        let the_site = MSite::build(&valid_line2, 2).unwrap_or_else(|err| {
             eprintln!("failed parsing site: {err} {valid_line2}");
             std::process::exit(1);
         });
@@ -231,19 +297,56 @@ mod tests {
     #[test]
     fn build_with_missing_field() {
         let invalid_line1: String = "chr1 1 + CpG 0".to_string();
-        assert!(MSite::build(&invalid_line1).is_err())
+        assert!(matches!(
+            MSite::build(&invalid_line1, 1),
+            Err(MSiteError::MissingField { field: "n_reads", .. })
+        ));
     }
 
     #[test]
-    #[should_panic]
     fn build_with_invalid_position() {
         let invalid_line1: String = "chr1 -10 + CpG 0 0".to_string();
-        let _  = MSite::build(&invalid_line1);
+        assert!(matches!(
+            MSite::build(&invalid_line1, 1),
+            Err(MSiteError::BadInt { field: "pos", .. })
+        ));
     }
 
     #[test]
     fn build_with_invalid_methylation_level() {
         let invalid_line1: String = "chr1 0 + CpG 1.2 10".to_string();
-        assert!(MSite::build(&invalid_line1).is_err())
+        assert!(matches!(
+            MSite::build(&invalid_line1, 1),
+            Err(MSiteError::MethOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn diff_test_identical_samples_is_not_significant() {
+        let a = MSite::build("chr1 0 + CpG 0.5 100", 1).unwrap();
+        let b = MSite::build("chr1 0 + CpG 0.5 100", 1).unwrap();
+        assert!(a.diff_test(&b) > 0.9);
+    }
+
+    #[test]
+    fn diff_test_extreme_difference_is_significant() {
+        let a = MSite::build("chr1 0 + CpG 0.0 50", 1).unwrap();
+        let b = MSite::build("chr1 0 + CpG 1.0 50", 1).unwrap();
+        assert!(a.diff_test(&b) < 0.001);
+    }
+
+    #[test]
+    fn diff_test_zero_coverage_returns_one() {
+        let a = MSite::build("chr1 0 + CpG 0 0", 1).unwrap();
+        let b = MSite::build("chr1 0 + CpG 0 0", 1).unwrap();
+        assert_eq!(a.diff_test(&b), 1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn diff_test_mismatched_site_panics() {
+        let a = MSite::build("chr1 0 + CpG 0.5 10", 1).unwrap();
+        let b = MSite::build("chr2 0 + CpG 0.5 10", 1).unwrap();
+        let _ = a.diff_test(&b);
     }
 }
@@ -0,0 +1,72 @@
+//! Small numeric helpers for significance testing, kept private to
+//! the crate since they exist only to support
+//! [`MSite::diff_test`](crate::MSite::diff_test).
+
+/// Natural log of the gamma function, via the Lanczos approximation
+/// (g = 7, n = 9), accurate to double precision for the non-negative
+/// arguments used here.
+fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula, ln(gamma(x)) = ln(pi / sin(pi x)) - ln(gamma(1 - x))
+        let pi = std::f64::consts::PI;
+        (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x)
+    } else {
+        let x = x - 1.0;
+        let mut a = COEFFS[0];
+        let t = x + G + 0.5;
+        for (i, c) in COEFFS.iter().enumerate().skip(1) {
+            a += c / (x + i as f64);
+        }
+        0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+    }
+}
+
+/// `ln(n choose k)`, via `ln_gamma`.
+fn ln_choose(n: u64, k: u64) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    ln_gamma(n as f64 + 1.0) - ln_gamma(k as f64 + 1.0) - ln_gamma((n - k) as f64 + 1.0)
+}
+
+/// Two-sided Fisher's exact test p-value for the 2x2 table
+/// `[[a, b], [c, d]]`, summing the hypergeometric probability of
+/// every table with the same margins that is no more likely than the
+/// observed one.
+pub(crate) fn fisher_exact(a: u64, b: u64, c: u64, d: u64) -> f64 {
+    let r1 = a + b;
+    let r2 = c + d;
+    let c1 = a + c;
+    let n = r1 + r2;
+
+    let ln_denom = ln_choose(n, c1);
+    let ln_prob = |k: u64| ln_choose(r1, k) + ln_choose(r2, c1 - k) - ln_denom;
+
+    let observed = ln_prob(a);
+    // tables range over every feasible top-left cell k, i.e. those
+    // for which both binomial coefficients above are defined
+    let lo = c1.saturating_sub(r2);
+    let hi = r1.min(c1);
+
+    // probabilities sum to 1 over [lo, hi]; accept floating point
+    // slop so the observed table itself is always included
+    const EPS: f64 = 1e-9;
+    (lo..=hi)
+        .filter(|&k| ln_prob(k) <= observed + EPS)
+        .map(|k| ln_prob(k).exp())
+        .sum::<f64>()
+        .min(1.0)
+}